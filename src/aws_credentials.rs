@@ -0,0 +1,253 @@
+use crate::error::AppError;
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{debug, warn};
+
+const ECS_CREDENTIALS_HOST: &str = "http://169.254.170.2";
+const IMDS_HOST: &str = "http://169.254.169.254";
+const IMDS_TOKEN_TTL_SECONDS: &str = "21600";
+/// Refresh this far ahead of the advertised expiry so an in-flight request never
+/// races a credential rotation.
+const REFRESH_SKEW: Duration = Duration::from_secs(5 * 60);
+
+/// ECS/IMDS live at non-routable link-local addresses - off-EC2, with no
+/// `AWS_*` env vars, a connect attempt otherwise hangs until the OS's
+/// multi-minute default timeout instead of failing fast into the next
+/// credential source, stalling every Bedrock request that falls through to
+/// the chain. Give the metadata services the same short fuse the AWS SDKs do.
+static METADATA_CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(1))
+        .timeout(Duration::from_secs(1))
+        .build()
+        .expect("Failed to create metadata client")
+});
+
+/// Temporary credentials resolved from the environment or the AWS metadata services.
+#[derive(Clone, Debug)]
+pub struct ResolvedCredentials {
+    pub access_key: String,
+    pub secret_key: String,
+    pub session_token: Option<String>,
+    pub expiration: Option<DateTime<Utc>>,
+}
+
+impl ResolvedCredentials {
+    fn is_fresh(&self) -> bool {
+        match self.expiration {
+            Some(expiration) => Utc::now() + chrono::Duration::from_std(REFRESH_SKEW).unwrap() < expiration,
+            None => true,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct MetadataCredentials {
+    #[serde(rename = "AccessKeyId")]
+    access_key_id: String,
+    #[serde(rename = "SecretAccessKey")]
+    secret_access_key: String,
+    #[serde(rename = "Token")]
+    token: Option<String>,
+    #[serde(rename = "Expiration")]
+    expiration: Option<DateTime<Utc>>,
+}
+
+impl From<MetadataCredentials> for ResolvedCredentials {
+    fn from(value: MetadataCredentials) -> Self {
+        Self {
+            access_key: value.access_key_id,
+            secret_key: value.secret_access_key,
+            session_token: value.token,
+            expiration: value.expiration,
+        }
+    }
+}
+
+static CREDENTIALS_CACHE: Lazy<RwLock<HashMap<String, ResolvedCredentials>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Resolves AWS credentials for `region`, following the standard chain: environment
+/// variables, then ECS container credentials, then IMDSv2. Results are cached per
+/// region and refreshed automatically a few minutes before they expire.
+pub async fn resolve_credentials(region: &str) -> Result<ResolvedCredentials, AppError> {
+    if let Some(cached) = CREDENTIALS_CACHE.read().await.get(region) {
+        if cached.is_fresh() {
+            return Ok(cached.clone());
+        }
+    }
+
+    let resolved = match resolve_from_environment() {
+        Some(creds) => creds,
+        None => match fetch_from_ecs().await {
+            Ok(creds) => creds,
+            Err(_) => fetch_from_imds().await?,
+        },
+    };
+
+    CREDENTIALS_CACHE
+        .write()
+        .await
+        .insert(region.to_string(), resolved.clone());
+    Ok(resolved)
+}
+
+fn resolve_from_environment() -> Option<ResolvedCredentials> {
+    let access_key = std::env::var("AWS_ACCESS_KEY_ID").ok()?;
+    let secret_key = std::env::var("AWS_SECRET_ACCESS_KEY").ok()?;
+    let session_token = std::env::var("AWS_SESSION_TOKEN").ok();
+    debug!("Resolved AWS credentials from environment variables");
+    Some(ResolvedCredentials {
+        access_key,
+        secret_key,
+        session_token,
+        expiration: None,
+    })
+}
+
+async fn fetch_from_ecs() -> Result<ResolvedCredentials, AppError> {
+    let relative_uri = std::env::var("AWS_CONTAINER_CREDENTIALS_RELATIVE_URI")
+        .map_err(|_| AppError::CredentialsUnavailable)?;
+    let url = format!("{}{}", ECS_CREDENTIALS_HOST, relative_uri);
+
+    debug!("Resolving AWS credentials from the ECS container credentials endpoint");
+    let response = METADATA_CLIENT
+        .get(&url)
+        .send()
+        .await
+        .map_err(|_| AppError::CredentialsUnavailable)?
+        .json::<MetadataCredentials>()
+        .await
+        .map_err(|_| AppError::CredentialsUnavailable)?;
+    Ok(response.into())
+}
+
+async fn fetch_from_imds() -> Result<ResolvedCredentials, AppError> {
+    debug!("Resolving AWS credentials from IMDSv2");
+    let client = &*METADATA_CLIENT;
+
+    let token = client
+        .put(format!("{}/latest/api/token", IMDS_HOST))
+        .header("x-aws-ec2-metadata-token-ttl-seconds", IMDS_TOKEN_TTL_SECONDS)
+        .send()
+        .await
+        .map_err(|_| AppError::CredentialsUnavailable)?
+        .text()
+        .await
+        .map_err(|_| AppError::CredentialsUnavailable)?;
+
+    let role = client
+        .get(format!(
+            "{}/latest/meta-data/iam/security-credentials/",
+            IMDS_HOST
+        ))
+        .header("x-aws-ec2-metadata-token", &token)
+        .send()
+        .await
+        .map_err(|_| AppError::CredentialsUnavailable)?
+        .text()
+        .await
+        .map_err(|_| AppError::CredentialsUnavailable)?;
+    let role = role.lines().next().ok_or(AppError::CredentialsUnavailable)?;
+
+    let response = client
+        .get(format!(
+            "{}/latest/meta-data/iam/security-credentials/{}",
+            IMDS_HOST, role
+        ))
+        .header("x-aws-ec2-metadata-token", &token)
+        .send()
+        .await
+        .map_err(|_| AppError::CredentialsUnavailable)?
+        .json::<MetadataCredentials>()
+        .await
+        .map_err(|e| {
+            warn!("Failed to parse IMDS credentials response: {}", e);
+            AppError::CredentialsUnavailable
+        })?;
+    Ok(response.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    // `resolve_from_environment` reads process-wide env vars, so serialize
+    // the tests that touch them to avoid one test observing another's vars.
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    fn credentials_expiring_in(duration: chrono::Duration) -> ResolvedCredentials {
+        ResolvedCredentials {
+            access_key: "AKIAEXAMPLE".to_string(),
+            secret_key: "secret".to_string(),
+            session_token: None,
+            expiration: Some(Utc::now() + duration),
+        }
+    }
+
+    #[test]
+    fn is_fresh_with_no_expiration_never_needs_a_refresh() {
+        let credentials = ResolvedCredentials {
+            access_key: "AKIAEXAMPLE".to_string(),
+            secret_key: "secret".to_string(),
+            session_token: None,
+            expiration: None,
+        };
+        assert!(credentials.is_fresh());
+    }
+
+    #[test]
+    fn is_fresh_well_before_expiration() {
+        let credentials = credentials_expiring_in(chrono::Duration::hours(1));
+        assert!(credentials.is_fresh());
+    }
+
+    #[test]
+    fn is_fresh_is_false_once_inside_the_refresh_skew_window() {
+        // Expires in 4 minutes, inside the 5-minute REFRESH_SKEW - an
+        // in-flight request must not be handed credentials this close to
+        // rotating out from under it.
+        let credentials = credentials_expiring_in(chrono::Duration::minutes(4));
+        assert!(!credentials.is_fresh());
+    }
+
+    #[test]
+    fn is_fresh_is_false_once_already_expired() {
+        let credentials = credentials_expiring_in(chrono::Duration::minutes(-1));
+        assert!(!credentials.is_fresh());
+    }
+
+    #[test]
+    fn resolve_from_environment_reads_the_standard_aws_env_vars() {
+        let _guard = ENV_LOCK.lock().unwrap();
+        std::env::set_var("AWS_ACCESS_KEY_ID", "AKIAEXAMPLE");
+        std::env::set_var("AWS_SECRET_ACCESS_KEY", "secret");
+        std::env::remove_var("AWS_SESSION_TOKEN");
+
+        let credentials = resolve_from_environment().expect("env vars are set");
+
+        assert_eq!(credentials.access_key, "AKIAEXAMPLE");
+        assert_eq!(credentials.secret_key, "secret");
+        assert_eq!(credentials.session_token, None);
+        assert_eq!(credentials.expiration, None);
+
+        std::env::remove_var("AWS_ACCESS_KEY_ID");
+        std::env::remove_var("AWS_SECRET_ACCESS_KEY");
+    }
+
+    #[test]
+    fn resolve_from_environment_falls_through_when_access_key_is_unset() {
+        let _guard = ENV_LOCK.lock().unwrap();
+        std::env::remove_var("AWS_ACCESS_KEY_ID");
+        std::env::set_var("AWS_SECRET_ACCESS_KEY", "secret");
+
+        assert!(resolve_from_environment().is_none());
+
+        std::env::remove_var("AWS_SECRET_ACCESS_KEY");
+    }
+}
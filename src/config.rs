@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+use std::env;
+
+/// How a provider authenticates outbound requests.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AuthScheme {
+    /// Prefer `x-magicapi-api-key` as a bearer token, falling back to
+    /// passing through a client-supplied `authorization` header untouched.
+    BearerOrPassthrough,
+    /// Require and forward the client's `authorization` header as-is.
+    PassthroughAuthorization,
+    /// Signed out-of-band (e.g. AWS SigV4); no static auth header to copy.
+    Signed,
+}
+
+/// Registration for a single upstream provider, loaded from `AppConfig` so new
+/// OpenAI-compatible or self-hosted endpoints can be added purely through
+/// configuration.
+#[derive(Clone, Debug)]
+pub struct ProviderConfig {
+    /// For a region-scoped signed provider (see `default_region` below), may
+    /// contain the literal placeholder `{AWS_REGION}`, substituted per-request
+    /// with the resolved region by that provider's `base_url`/`get_signing_host`
+    /// (e.g. `BedrockProvider::resolved_base_url`). Used as-is otherwise.
+    pub base_url: String,
+    pub auth_scheme: AuthScheme,
+    pub requires_signing: bool,
+    /// Extra client header name -> upstream header name passthroughs.
+    pub header_mappings: HashMap<String, String>,
+    /// Default AWS region for providers whose endpoint is region-scoped (e.g.
+    /// Bedrock), overridable per-request via the `x-aws-region` header.
+    /// `None` for providers that aren't region-scoped.
+    pub default_region: Option<String>,
+}
+
+/// Runtime configuration for the gateway, populated from environment variables
+/// with sensible defaults so the gateway runs out of the box in development.
+#[derive(Clone, Debug)]
+pub struct AppConfig {
+    pub max_connections: usize,
+    /// Maximum number of replay attempts for a transient upstream failure, not
+    /// counting the initial attempt.
+    pub max_retries: u32,
+    /// Base delay for exponential backoff between retries, before jitter.
+    pub retry_base_delay_ms: u64,
+    /// Upstream HTTP status codes that are safe to retry.
+    pub retryable_status_codes: Vec<u16>,
+    /// Whether to mount the `/metrics` Prometheus endpoint.
+    pub metrics_enabled: bool,
+    /// Registered providers, keyed by the name used in the proxy path
+    /// (e.g. `/openai/...`).
+    pub providers: HashMap<String, ProviderConfig>,
+}
+
+fn default_providers() -> HashMap<String, ProviderConfig> {
+    let mut providers = HashMap::new();
+    providers.insert(
+        "openai".to_string(),
+        ProviderConfig {
+            base_url: "https://api.openai.com".to_string(),
+            auth_scheme: AuthScheme::BearerOrPassthrough,
+            requires_signing: false,
+            header_mappings: HashMap::new(),
+            default_region: None,
+        },
+    );
+    providers.insert(
+        "anthropic".to_string(),
+        ProviderConfig {
+            base_url: "https://api.anthropic.com".to_string(),
+            auth_scheme: AuthScheme::PassthroughAuthorization,
+            requires_signing: false,
+            header_mappings: HashMap::new(),
+            default_region: None,
+        },
+    );
+    providers.insert(
+        "groq".to_string(),
+        ProviderConfig {
+            base_url: "https://api.groq.com/openai".to_string(),
+            auth_scheme: AuthScheme::PassthroughAuthorization,
+            requires_signing: false,
+            header_mappings: HashMap::new(),
+            default_region: None,
+        },
+    );
+    providers.insert(
+        "bedrock".to_string(),
+        ProviderConfig {
+            // `{AWS_REGION}` is substituted per-request by `BedrockProvider`
+            // with the resolved region (`x-aws-region` header, else
+            // `default_region` below) - see `BedrockProvider::resolved_base_url`.
+            base_url: "https://bedrock-runtime.{AWS_REGION}.amazonaws.com".to_string(),
+            auth_scheme: AuthScheme::Signed,
+            requires_signing: true,
+            header_mappings: HashMap::new(),
+            default_region: Some(
+                env::var("AWS_DEFAULT_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+            ),
+        },
+    );
+    providers
+}
+
+impl AppConfig {
+    pub fn new() -> Self {
+        Self {
+            max_connections: env::var("MAX_CONNECTIONS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(32),
+            max_retries: env::var("MAX_RETRIES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(2),
+            retry_base_delay_ms: env::var("RETRY_BASE_DELAY_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(200),
+            retryable_status_codes: env::var("RETRYABLE_STATUS_CODES")
+                .ok()
+                .map(|v| {
+                    v.split(',')
+                        .filter_map(|code| code.trim().parse().ok())
+                        .collect()
+                })
+                .unwrap_or_else(|| vec![429, 500, 502, 503, 504]),
+            metrics_enabled: env::var("METRICS_ENABLED")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(true),
+            providers: default_providers(),
+        }
+    }
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
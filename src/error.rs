@@ -0,0 +1,56 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+use thiserror::Error;
+
+/// Errors surfaced to clients of the gateway, each mapped to an appropriate
+/// HTTP status code in `IntoResponse`.
+#[derive(Error, Debug)]
+pub enum AppError {
+    #[error("unsupported provider")]
+    UnsupportedProvider,
+    #[error("unsupported model")]
+    UnsupportedModel,
+    #[error("invalid request format")]
+    InvalidRequestFormat,
+    #[error("invalid HTTP method")]
+    InvalidMethod,
+    #[error("invalid header")]
+    InvalidHeader,
+    #[error("missing API key")]
+    MissingApiKey,
+    #[error("AWS credentials unavailable")]
+    CredentialsUnavailable,
+    #[error("invalid event stream frame")]
+    InvalidEventStreamFrame,
+    #[error("upstream request failed: {0}")]
+    UpstreamRequest(#[from] reqwest::Error),
+    #[error("invalid JSON body: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("invalid body: {0}")]
+    Body(#[from] axum::Error),
+    #[error("invalid status code: {0}")]
+    InvalidStatusCode(#[from] axum::http::status::InvalidStatusCode),
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            AppError::UnsupportedProvider | AppError::UnsupportedModel => StatusCode::BAD_REQUEST,
+            AppError::InvalidRequestFormat
+            | AppError::InvalidMethod
+            | AppError::InvalidHeader
+            | AppError::Json(_)
+            | AppError::Body(_)
+            | AppError::InvalidStatusCode(_) => StatusCode::BAD_REQUEST,
+            AppError::MissingApiKey | AppError::CredentialsUnavailable => StatusCode::UNAUTHORIZED,
+            AppError::InvalidEventStreamFrame => StatusCode::BAD_GATEWAY,
+            AppError::UpstreamRequest(_) => StatusCode::BAD_GATEWAY,
+        };
+
+        (status, Json(json!({ "error": self.to_string() }))).into_response()
+    }
+}
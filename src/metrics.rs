@@ -0,0 +1,158 @@
+use crate::config::AppConfig;
+use axum::{
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use once_cell::sync::Lazy;
+use prometheus::{
+    CounterVec, Encoder, HistogramVec, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+
+static REGISTRY: Lazy<Registry> = Lazy::new(Registry::new);
+
+pub static REQUESTS_TOTAL: Lazy<CounterVec> = Lazy::new(|| {
+    let counter = CounterVec::new(
+        Opts::new("gateway_requests_total", "Total requests proxied, by provider and model"),
+        &["provider", "model"],
+    )
+    .expect("metric can be created");
+    REGISTRY.register(Box::new(counter.clone())).expect("metric can be registered");
+    counter
+});
+
+pub static ERRORS_TOTAL: Lazy<CounterVec> = Lazy::new(|| {
+    let counter = CounterVec::new(
+        Opts::new("gateway_errors_total", "Upstream errors, by provider and status class"),
+        &["provider", "status_class"],
+    )
+    .expect("metric can be created");
+    REGISTRY.register(Box::new(counter.clone())).expect("metric can be registered");
+    counter
+});
+
+pub static UPSTREAM_LATENCY_SECONDS: Lazy<HistogramVec> = Lazy::new(|| {
+    let histogram = HistogramVec::new(
+        prometheus::HistogramOpts::new(
+            "gateway_upstream_latency_seconds",
+            "Upstream request latency in seconds, by provider and model",
+        ),
+        &["provider", "model"],
+    )
+    .expect("metric can be created");
+    REGISTRY.register(Box::new(histogram.clone())).expect("metric can be registered");
+    histogram
+});
+
+pub static IN_FLIGHT: Lazy<IntGaugeVec> = Lazy::new(|| {
+    let gauge = IntGaugeVec::new(
+        Opts::new("gateway_in_flight_requests", "Requests currently in flight, by provider"),
+        &["provider"],
+    )
+    .expect("metric can be created");
+    REGISTRY.register(Box::new(gauge.clone())).expect("metric can be registered");
+    gauge
+});
+
+pub static STREAMING_SPLIT_TOTAL: Lazy<CounterVec> = Lazy::new(|| {
+    let counter = CounterVec::new(
+        Opts::new("gateway_streaming_split_total", "Responses split by streaming vs buffered, by provider"),
+        &["provider", "mode"],
+    )
+    .expect("metric can be created");
+    REGISTRY.register(Box::new(counter.clone())).expect("metric can be registered");
+    counter
+});
+
+pub static BYTES_IN_TOTAL: Lazy<CounterVec> = Lazy::new(|| {
+    let counter = CounterVec::new(
+        Opts::new("gateway_bytes_in_total", "Request bytes received from clients, by provider"),
+        &["provider"],
+    )
+    .expect("metric can be created");
+    REGISTRY.register(Box::new(counter.clone())).expect("metric can be registered");
+    counter
+});
+
+pub static BYTES_OUT_TOTAL: Lazy<CounterVec> = Lazy::new(|| {
+    let counter = CounterVec::new(
+        Opts::new("gateway_bytes_out_total", "Response bytes returned to clients, by provider"),
+        &["provider"],
+    )
+    .expect("metric can be created");
+    REGISTRY.register(Box::new(counter.clone())).expect("metric can be registered");
+    counter
+});
+
+pub static PROMPT_TOKENS_TOTAL: Lazy<CounterVec> = Lazy::new(|| {
+    let counter = CounterVec::new(
+        Opts::new("gateway_prompt_tokens_total", "Prompt tokens billed upstream, by provider and model"),
+        &["provider", "model"],
+    )
+    .expect("metric can be created");
+    REGISTRY.register(Box::new(counter.clone())).expect("metric can be registered");
+    counter
+});
+
+pub static COMPLETION_TOKENS_TOTAL: Lazy<CounterVec> = Lazy::new(|| {
+    let counter = CounterVec::new(
+        Opts::new("gateway_completion_tokens_total", "Completion tokens billed upstream, by provider and model"),
+        &["provider", "model"],
+    )
+    .expect("metric can be created");
+    REGISTRY.register(Box::new(counter.clone())).expect("metric can be registered");
+    counter
+});
+
+/// Maps an HTTP status code to the coarse class (`2xx`, `4xx`, `5xx`, ...) used
+/// to keep the error counter's cardinality low.
+pub fn status_class(status: u16) -> &'static str {
+    match status / 100 {
+        2 => "2xx",
+        3 => "3xx",
+        4 => "4xx",
+        5 => "5xx",
+        _ => "other",
+    }
+}
+
+/// Parses `usage.prompt_tokens`/`usage.completion_tokens` out of a buffered
+/// JSON response body and records them against `provider`/`model`, if present.
+pub fn record_token_usage(provider: &str, model: &str, body: &[u8]) {
+    let Ok(value) = serde_json::from_slice::<serde_json::Value>(body) else {
+        return;
+    };
+    if let Some(prompt_tokens) = value["usage"]["prompt_tokens"].as_u64() {
+        PROMPT_TOKENS_TOTAL
+            .with_label_values(&[provider, model])
+            .inc_by(prompt_tokens as f64);
+    }
+    if let Some(completion_tokens) = value["usage"]["completion_tokens"].as_u64() {
+        COMPLETION_TOKENS_TOTAL
+            .with_label_values(&[provider, model])
+            .inc_by(completion_tokens as f64);
+    }
+}
+
+/// Renders all registered metrics in Prometheus text exposition format.
+pub async fn metrics_handler() -> Response {
+    let encoder = TextEncoder::new();
+    let metric_families = REGISTRY.gather();
+    let mut buffer = Vec::new();
+    if let Err(e) = encoder.encode(&metric_families, &mut buffer) {
+        tracing::error!("Failed to encode Prometheus metrics: {}", e);
+        return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "failed to encode metrics").into_response();
+    }
+    ([(axum::http::header::CONTENT_TYPE, encoder.format_type().to_string())], buffer).into_response()
+}
+
+/// Mounts the `/metrics` route onto `router` when `config.metrics_enabled`
+/// (read from the `METRICS_ENABLED` env var, like the other env-driven
+/// settings in `AppConfig`). Returns `router` unchanged when disabled.
+pub fn mount(router: Router, config: &AppConfig) -> Router {
+    if config.metrics_enabled {
+        router.route("/metrics", get(metrics_handler))
+    } else {
+        router
+    }
+}
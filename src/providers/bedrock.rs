@@ -1,5 +1,8 @@
 use super::Provider;
+use crate::aws_credentials::resolve_credentials;
+use crate::config::ProviderConfig;
 use crate::error::AppError;
+use crate::signing::SigningCredentials;
 use async_trait::async_trait;
 use axum::{
     body::{Body, Bytes},
@@ -8,21 +11,53 @@ use axum::{
 use serde_json::{json, Value};
 use tracing::{debug, error};
 
+/// Placeholder substituted with the resolved region (see `resolve_region`) in
+/// a `ProviderConfig::base_url` that routes per-request to a region-scoped
+/// endpoint, e.g. `https://bedrock-runtime.{AWS_REGION}.amazonaws.com`. A
+/// `base_url` with no placeholder is used as-is, for a signed config entry
+/// that targets one fixed endpoint regardless of `x-aws-region`.
+const AWS_REGION_PLACEHOLDER: &str = "{AWS_REGION}";
+
 pub struct BedrockProvider {
-    base_url: String,
-    region: String,
+    default_region: String,
+    base_url_template: String,
+    header_mappings: Vec<(String, String)>,
 }
 
 impl BedrockProvider {
-    pub fn new() -> Self {
-        let region = "us-east-1".to_string();
-        debug!("Initializing BedrockProvider with region: {}", region);
+    pub fn new(config: &ProviderConfig) -> Self {
+        let default_region = config
+            .default_region
+            .clone()
+            .unwrap_or_else(|| "us-east-1".to_string());
+        debug!("Initializing BedrockProvider with default region: {}", default_region);
         Self {
-            base_url: format!("https://bedrock-runtime.{}.amazonaws.com", region),
-            region,
+            default_region,
+            base_url_template: config.base_url.clone(),
+            header_mappings: config.header_mappings.clone().into_iter().collect(),
         }
     }
 
+    /// The region a request should be routed and signed against: the
+    /// client-supplied `x-aws-region` header if present, else the default.
+    /// `base_url`, `get_signing_host` and `get_signing_credentials` all derive
+    /// from this so the endpoint, the signed `Host`, and the signed scope can
+    /// never disagree.
+    fn resolve_region(&self, headers: &HeaderMap) -> String {
+        headers
+            .get("x-aws-region")
+            .and_then(|h| h.to_str().ok())
+            .unwrap_or(&self.default_region)
+            .to_string()
+    }
+
+    /// `config.base_url` with `AWS_REGION_PLACEHOLDER` substituted for the
+    /// resolved region, still including the `https://` scheme.
+    fn resolved_base_url(&self, headers: &HeaderMap) -> String {
+        self.base_url_template
+            .replace(AWS_REGION_PLACEHOLDER, &self.resolve_region(headers))
+    }
+
     fn get_model_name(&self, path: &str) -> String {
         if let Some(model) = path.split('/').last() {
             model.to_string()
@@ -99,8 +134,8 @@ impl BedrockProvider {
 
 #[async_trait]
 impl Provider for BedrockProvider {
-    fn base_url(&self) -> &str {
-        &self.base_url
+    fn base_url(&self, headers: &HeaderMap) -> String {
+        self.resolved_base_url(headers)
     }
 
     fn name(&self) -> &str {
@@ -120,27 +155,31 @@ impl Provider for BedrockProvider {
     }
 
     fn process_headers(&self, headers: &HeaderMap) -> Result<HeaderMap, AppError> {
+        // The `x-aws-*` headers (access key, secret key, session token, region)
+        // are only ever consumed locally by `get_signing_credentials` and
+        // `resolve_region` - they must never be forwarded upstream, or we'd be
+        // handing AWS secret keys to Bedrock as opaque request headers. Only
+        // headers explicitly listed in `header_mappings` make it through.
         let mut final_headers = HeaderMap::new();
-        
-        // Add standard headers
         final_headers.insert(
             http::header::CONTENT_TYPE,
             http::header::HeaderValue::from_static("application/json"),
         );
 
-        // Preserve AWS specific headers
-        for (key, value) in headers {
-            if key.as_str().starts_with("x-aws-") {
-                final_headers.insert(key.clone(), value.clone());
+        for (from, to) in &self.header_mappings {
+            if let Some(value) = headers.get(from.as_str()) {
+                let header_name = http::header::HeaderName::from_bytes(to.as_bytes())
+                    .map_err(|_| AppError::InvalidHeader)?;
+                final_headers.insert(header_name, value.clone());
             }
         }
 
         Ok(final_headers)
     }
 
-    fn transform_path(&self, path: &str) -> String {
-        debug!("Transforming path: {}", path);
-        
+    fn transform_path(&self, path: &str, stream: bool) -> String {
+        debug!("Transforming path: {} (stream={})", path, stream);
+
         let model = if path.contains("chat/completions") {
             "amazon.titan-text-premier-v1:0"
         } else if let Some(model) = path.split('/').last() {
@@ -148,31 +187,325 @@ impl Provider for BedrockProvider {
         } else {
             "amazon.titan-text-premier-v1:0"
         };
-        
+
         debug!("Using model for path: {}", model);
-        format!("/model/{}/invoke", model)
+        let operation = if stream {
+            "invoke-with-response-stream"
+        } else {
+            "invoke"
+        };
+        format!("/model/{}/{}", model, operation)
     }
 
     fn requires_signing(&self) -> bool {
         true
     }
 
-    fn get_signing_credentials(&self, headers: &HeaderMap) -> Option<(String, String, String)> {
-        let access_key = headers.get("x-aws-access-key-id")?.to_str().ok()?;
-        let secret_key = headers.get("x-aws-secret-access-key")?.to_str().ok()?;
-        let region = headers
-            .get("x-aws-region")
-            .and_then(|h| h.to_str().ok())
-            .unwrap_or(&self.region);
-        
-        Some((
-            access_key.to_string(),
-            secret_key.to_string(),
-            region.to_string()
+    fn resolve_model(&self, path: &str, _body: &Bytes) -> String {
+        if path.contains("chat/completions") {
+            // transform_path always routes chat/completions to Titan,
+            // regardless of what the client's body named - match it.
+            "amazon.titan-text-premier-v1:0".to_string()
+        } else if let Some(model) = path.split('/').last() {
+            model.to_string()
+        } else {
+            "amazon.titan-text-premier-v1:0".to_string()
+        }
+    }
+
+    async fn get_signing_credentials(&self, headers: &HeaderMap) -> Result<SigningCredentials, AppError> {
+        let region = self.resolve_region(headers);
+
+        if let (Some(access_key), Some(secret_key)) = (
+            headers.get("x-aws-access-key-id").and_then(|h| h.to_str().ok()),
+            headers.get("x-aws-secret-access-key").and_then(|h| h.to_str().ok()),
+        ) {
+            debug!("Using AWS credentials supplied on the request headers");
+            return Ok(SigningCredentials {
+                access_key: access_key.to_string(),
+                secret_key: secret_key.to_string(),
+                session_token: headers
+                    .get("x-aws-security-token")
+                    .and_then(|h| h.to_str().ok())
+                    .map(|s| s.to_string()),
+                region,
+            });
+        }
+
+        debug!("No AWS credentials on the request, falling back to the credential chain");
+        let resolved = resolve_credentials(&region).await?;
+        Ok(SigningCredentials {
+            access_key: resolved.access_key,
+            secret_key: resolved.secret_key,
+            session_token: resolved.session_token,
+            region,
+        })
+    }
+
+    fn get_signing_host(&self, headers: &HeaderMap) -> String {
+        self.resolved_base_url(headers)
+            .trim_start_matches("https://")
+            .trim_start_matches("http://")
+            .to_string()
+    }
+
+    async fn transform_response_body(&self, model: &str, body: Bytes) -> Result<Bytes, AppError> {
+        let value: Value = serde_json::from_slice(&body)?;
+        debug!("Transforming Bedrock response body for model {}: {:#?}", model, value);
+
+        let (content, prompt_tokens, completion_tokens) = if model.contains("titan") {
+            let result = value["results"].get(0).ok_or(AppError::InvalidRequestFormat)?;
+            (
+                result["outputText"].as_str().unwrap_or("").to_string(),
+                value["inputTextTokenCount"].as_u64().unwrap_or(0),
+                result["tokenCount"].as_u64().unwrap_or(0),
+            )
+        } else if model.contains("anthropic") {
+            (
+                value["completion"].as_str().unwrap_or("").to_string(),
+                0,
+                0,
+            )
+        } else {
+            error!("Unsupported model for response transformation: {}", model);
+            return Err(AppError::UnsupportedModel);
+        };
+
+        let chat_completion = json!({
+            "id": "chatcmpl-bedrock",
+            "object": "chat.completion",
+            "model": model,
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": content },
+                "finish_reason": "stop"
+            }],
+            "usage": {
+                "prompt_tokens": prompt_tokens,
+                "completion_tokens": completion_tokens,
+                "total_tokens": prompt_tokens + completion_tokens
+            }
+        });
+
+        Ok(Bytes::from(serde_json::to_vec(&chat_completion)?))
+    }
+
+    fn transform_streaming_chunk(&self, model: &str, chunk: Bytes) -> Result<Option<Bytes>, AppError> {
+        let value: Value = serde_json::from_slice(&chunk)?;
+
+        let delta = if model.contains("titan") {
+            value["outputText"].as_str()
+        } else if model.contains("anthropic") {
+            value["completion"].as_str()
+        } else {
+            error!("Unsupported model for streaming: {}", model);
+            return Err(AppError::UnsupportedModel);
+        };
+
+        let Some(delta) = delta.filter(|text| !text.is_empty()) else {
+            return Ok(None);
+        };
+
+        let sse_chunk = json!({
+            "id": "chatcmpl-bedrock",
+            "object": "chat.completion.chunk",
+            "choices": [{
+                "index": 0,
+                "delta": { "content": delta },
+                "finish_reason": Value::Null
+            }]
+        });
+
+        Ok(Some(Bytes::from(format!(
+            "data: {}\n\n",
+            serde_json::to_string(&sse_chunk)?
+        ))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AuthScheme;
+    use std::collections::HashMap;
+
+    fn config(base_url: &str, header_mappings: HashMap<String, String>) -> ProviderConfig {
+        ProviderConfig {
+            base_url: base_url.to_string(),
+            auth_scheme: AuthScheme::Signed,
+            requires_signing: true,
+            header_mappings,
+            default_region: Some("us-east-1".to_string()),
+        }
+    }
+
+    #[test]
+    fn base_url_substitutes_the_region_placeholder_from_config() {
+        let provider = BedrockProvider::new(&config(
+            "https://bedrock-runtime.{AWS_REGION}.amazonaws-us-gov.com",
+            HashMap::new(),
+        ));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-aws-region", "us-gov-west-1".parse().unwrap());
+
+        assert_eq!(
+            provider.base_url(&headers),
+            "https://bedrock-runtime.us-gov-west-1.amazonaws-us-gov.com"
+        );
+    }
+
+    #[test]
+    fn base_url_is_used_verbatim_when_it_has_no_placeholder() {
+        let provider = BedrockProvider::new(&config(
+            "https://bedrock-runtime.fixed-endpoint.example.com",
+            HashMap::new(),
+        ));
+
+        assert_eq!(
+            provider.base_url(&HeaderMap::new()),
+            "https://bedrock-runtime.fixed-endpoint.example.com"
+        );
+    }
+
+    #[test]
+    fn get_signing_host_matches_base_url_minus_scheme() {
+        let provider = BedrockProvider::new(&config(
+            "https://bedrock-runtime.{AWS_REGION}.amazonaws.com",
+            HashMap::new(),
+        ));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-aws-region", "eu-west-1".parse().unwrap());
+
+        assert_eq!(
+            provider.get_signing_host(&headers),
+            "bedrock-runtime.eu-west-1.amazonaws.com"
+        );
+        assert!(provider.base_url(&headers).ends_with(&provider.get_signing_host(&headers)));
+    }
+
+    #[test]
+    fn process_headers_passes_through_only_configured_mappings() {
+        let mut header_mappings = HashMap::new();
+        header_mappings.insert("x-request-id".to_string(), "x-amzn-trace-id".to_string());
+        let provider = BedrockProvider::new(&config(
+            "https://bedrock-runtime.{AWS_REGION}.amazonaws.com",
+            header_mappings,
+        ));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-request-id", "abc-123".parse().unwrap());
+        headers.insert("x-aws-access-key-id", "AKIA_SHOULD_NOT_FORWARD".parse().unwrap());
+
+        let final_headers = provider.process_headers(&headers).unwrap();
+
+        assert_eq!(final_headers.get("x-amzn-trace-id").unwrap(), "abc-123");
+        assert!(final_headers.get("x-aws-access-key-id").is_none());
+        assert!(final_headers.get("x-request-id").is_none());
+    }
+
+    fn provider() -> BedrockProvider {
+        BedrockProvider::new(&config(
+            "https://bedrock-runtime.{AWS_REGION}.amazonaws.com",
+            HashMap::new(),
         ))
     }
 
-    fn get_signing_host(&self) -> String {
-        format!("bedrock-runtime.{}.amazonaws.com", self.region)
+    #[tokio::test]
+    async fn transform_response_body_maps_a_titan_response() {
+        let body = Bytes::from(
+            json!({
+                "inputTextTokenCount": 12,
+                "results": [{ "outputText": "hello there", "tokenCount": 3 }]
+            })
+            .to_string(),
+        );
+
+        let chat_completion = provider()
+            .transform_response_body("amazon.titan-text-premier-v1:0", body)
+            .await
+            .unwrap();
+        let value: Value = serde_json::from_slice(&chat_completion).unwrap();
+
+        assert_eq!(value["choices"][0]["message"]["content"], "hello there");
+        assert_eq!(value["usage"]["prompt_tokens"], 12);
+        assert_eq!(value["usage"]["completion_tokens"], 3);
+        assert_eq!(value["usage"]["total_tokens"], 15);
+    }
+
+    #[tokio::test]
+    async fn transform_response_body_maps_an_anthropic_response() {
+        let body = Bytes::from(json!({ "completion": "hi back" }).to_string());
+
+        let chat_completion = provider()
+            .transform_response_body("anthropic.claude-v2", body)
+            .await
+            .unwrap();
+        let value: Value = serde_json::from_slice(&chat_completion).unwrap();
+
+        assert_eq!(value["choices"][0]["message"]["content"], "hi back");
+        // Anthropic's Bedrock response carries no token counts to report.
+        assert_eq!(value["usage"]["total_tokens"], 0);
+    }
+
+    #[tokio::test]
+    async fn transform_response_body_rejects_an_unrecognized_model() {
+        let body = Bytes::from(json!({ "anything": "goes" }).to_string());
+
+        let result = provider()
+            .transform_response_body("cohere.command-text-v14", body)
+            .await;
+
+        assert!(matches!(result, Err(AppError::UnsupportedModel)));
+    }
+
+    #[test]
+    fn transform_streaming_chunk_emits_an_sse_event_for_a_titan_delta() {
+        let chunk = Bytes::from(json!({ "outputText": "par" }).to_string());
+
+        let sse = provider()
+            .transform_streaming_chunk("amazon.titan-text-premier-v1:0", chunk)
+            .unwrap()
+            .expect("a non-empty delta produces an event");
+
+        assert!(sse.starts_with(b"data: "));
+        let payload: Value =
+            serde_json::from_slice(&sse["data: ".len()..sse.len() - 2]).unwrap();
+        assert_eq!(payload["choices"][0]["delta"]["content"], "par");
+    }
+
+    #[test]
+    fn transform_streaming_chunk_emits_an_sse_event_for_an_anthropic_delta() {
+        let chunk = Bytes::from(json!({ "completion": "tial" }).to_string());
+
+        let sse = provider()
+            .transform_streaming_chunk("anthropic.claude-v2", chunk)
+            .unwrap()
+            .expect("a non-empty delta produces an event");
+
+        let payload: Value =
+            serde_json::from_slice(&sse["data: ".len()..sse.len() - 2]).unwrap();
+        assert_eq!(payload["choices"][0]["delta"]["content"], "tial");
+    }
+
+    #[test]
+    fn transform_streaming_chunk_returns_none_for_an_empty_delta() {
+        let chunk = Bytes::from(json!({ "outputText": "" }).to_string());
+
+        let result = provider()
+            .transform_streaming_chunk("amazon.titan-text-premier-v1:0", chunk)
+            .unwrap();
+
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn transform_streaming_chunk_rejects_an_unrecognized_model() {
+        let chunk = Bytes::from(json!({ "anything": "goes" }).to_string());
+
+        let result = provider().transform_streaming_chunk("cohere.command-text-v14", chunk);
+
+        assert!(matches!(result, Err(AppError::UnsupportedModel)));
     }
-} 
\ No newline at end of file
+}
\ No newline at end of file
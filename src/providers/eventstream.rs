@@ -0,0 +1,207 @@
+use crate::error::AppError;
+use base64::Engine;
+use bytes::BytesMut;
+use serde_json::Value;
+
+/// Incrementally decodes AWS's binary event stream framing
+/// (`application/vnd.amazon.eventstream`) off a byte stream that may split or
+/// coalesce frames arbitrarily.
+///
+/// Frame layout: `[total_len u32][headers_len u32][prelude_crc u32][headers]
+/// [payload][message_crc u32]`. Header contents are ignored here since Bedrock's
+/// chunk payload is fully self-describing JSON; only the CRCs are validated.
+#[derive(Default)]
+pub struct EventStreamDecoder {
+    buffer: BytesMut,
+}
+
+impl EventStreamDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds newly received bytes and returns the raw payload of every frame
+    /// that is now fully buffered. Partial frames are retained for the next call.
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Vec<Vec<u8>>, AppError> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut payloads = Vec::new();
+        while let Some(payload) = self.try_decode_one()? {
+            payloads.push(payload);
+        }
+        Ok(payloads)
+    }
+
+    fn try_decode_one(&mut self) -> Result<Option<Vec<u8>>, AppError> {
+        const PRELUDE_LEN: usize = 8;
+        const PRELUDE_AND_CRC_LEN: usize = PRELUDE_LEN + 4;
+        // Bedrock never frames a single event anywhere near this large. Bound
+        // total_len before trusting it, so a corrupt (or hostile) length can't
+        // make us buffer forever waiting for bytes that will never arrive.
+        const MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+
+        if self.buffer.len() < PRELUDE_AND_CRC_LEN {
+            return Ok(None);
+        }
+
+        let total_len = u32::from_be_bytes(self.buffer[0..4].try_into().unwrap()) as usize;
+        let headers_len = u32::from_be_bytes(self.buffer[4..8].try_into().unwrap()) as usize;
+        let prelude_crc = u32::from_be_bytes(self.buffer[8..12].try_into().unwrap());
+
+        // Validate the prelude before deciding whether to wait for more bytes:
+        // the prelude itself is already fully buffered here, and total_len is
+        // otherwise untrusted input that determines how long we wait.
+        if crc32fast::hash(&self.buffer[0..PRELUDE_LEN]) != prelude_crc {
+            return Err(AppError::InvalidEventStreamFrame);
+        }
+        if total_len < PRELUDE_AND_CRC_LEN + headers_len + 4 || total_len > MAX_FRAME_LEN {
+            return Err(AppError::InvalidEventStreamFrame);
+        }
+
+        if self.buffer.len() < total_len {
+            return Ok(None);
+        }
+
+        let payload_start = PRELUDE_AND_CRC_LEN + headers_len;
+        let payload_end = total_len - 4;
+        let message_crc = u32::from_be_bytes(
+            self.buffer[payload_end..total_len].try_into().unwrap(),
+        );
+        if crc32fast::hash(&self.buffer[0..payload_end]) != message_crc {
+            return Err(AppError::InvalidEventStreamFrame);
+        }
+
+        let payload = self.buffer[payload_start..payload_end].to_vec();
+        let _ = self.buffer.split_to(total_len);
+        Ok(Some(payload))
+    }
+}
+
+/// Decodes one eventstream message payload (JSON with a base64 `bytes` field)
+/// into the raw model chunk bytes it wraps.
+pub fn decode_payload(payload: &[u8]) -> Result<Vec<u8>, AppError> {
+    let envelope: Value = serde_json::from_slice(payload)?;
+    let encoded = envelope["bytes"]
+        .as_str()
+        .ok_or(AppError::InvalidEventStreamFrame)?;
+    base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|_| AppError::InvalidEventStreamFrame)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds one valid eventstream frame (no headers) wrapping `payload`,
+    /// with correct prelude/message CRCs, mirroring what Bedrock sends.
+    fn build_frame(payload: &[u8]) -> Vec<u8> {
+        let headers_len: u32 = 0;
+        let total_len = (8 + 4 + headers_len as usize + payload.len() + 4) as u32;
+
+        let mut frame = Vec::new();
+        frame.extend_from_slice(&total_len.to_be_bytes());
+        frame.extend_from_slice(&headers_len.to_be_bytes());
+        let prelude_crc = crc32fast::hash(&frame[0..8]);
+        frame.extend_from_slice(&prelude_crc.to_be_bytes());
+        frame.extend_from_slice(payload);
+        let message_crc = crc32fast::hash(&frame[0..frame.len()]);
+        frame.extend_from_slice(&message_crc.to_be_bytes());
+        frame
+    }
+
+    #[test]
+    fn decodes_a_single_complete_frame() {
+        let frame = build_frame(b"hello");
+        let mut decoder = EventStreamDecoder::new();
+        let payloads = decoder.push(&frame).unwrap();
+        assert_eq!(payloads, vec![b"hello".to_vec()]);
+    }
+
+    #[test]
+    fn decodes_two_frames_coalesced_into_one_push() {
+        let mut both = build_frame(b"first");
+        both.extend(build_frame(b"second"));
+        let mut decoder = EventStreamDecoder::new();
+        let payloads = decoder.push(&both).unwrap();
+        assert_eq!(payloads, vec![b"first".to_vec(), b"second".to_vec()]);
+    }
+
+    #[test]
+    fn buffers_a_frame_split_across_pushes() {
+        let frame = build_frame(b"hello");
+        let (head, tail) = frame.split_at(frame.len() - 3);
+        let mut decoder = EventStreamDecoder::new();
+
+        assert_eq!(decoder.push(head).unwrap(), Vec::<Vec<u8>>::new());
+        assert_eq!(decoder.push(tail).unwrap(), vec![b"hello".to_vec()]);
+    }
+
+    #[test]
+    fn rejects_a_corrupt_prelude_crc() {
+        let mut frame = build_frame(b"hello");
+        frame[8] ^= 0xFF; // flip a byte of the prelude CRC itself
+        let mut decoder = EventStreamDecoder::new();
+        assert!(matches!(
+            decoder.push(&frame),
+            Err(AppError::InvalidEventStreamFrame)
+        ));
+    }
+
+    #[test]
+    fn rejects_a_total_len_too_small_for_its_own_framing() {
+        let mut frame = build_frame(b"hello");
+        // Shrink total_len below PRELUDE_AND_CRC_LEN + headers_len + 4, then
+        // recompute the prelude CRC (which covers total_len) so only the
+        // length check can reject the frame.
+        frame[0..4].copy_from_slice(&4u32.to_be_bytes());
+        let prelude_crc = crc32fast::hash(&frame[0..8]);
+        frame[8..12].copy_from_slice(&prelude_crc.to_be_bytes());
+        let mut decoder = EventStreamDecoder::new();
+        assert!(matches!(
+            decoder.push(&frame),
+            Err(AppError::InvalidEventStreamFrame)
+        ));
+    }
+
+    #[test]
+    fn rejects_a_total_len_above_the_max_frame_bound() {
+        let mut frame = build_frame(b"hello");
+        frame[0..4].copy_from_slice(&u32::MAX.to_be_bytes());
+        let prelude_crc = crc32fast::hash(&frame[0..8]);
+        frame[8..12].copy_from_slice(&prelude_crc.to_be_bytes());
+        let mut decoder = EventStreamDecoder::new();
+        assert!(matches!(
+            decoder.push(&frame),
+            Err(AppError::InvalidEventStreamFrame)
+        ));
+    }
+
+    #[test]
+    fn rejects_a_corrupt_message_crc() {
+        let mut frame = build_frame(b"hello");
+        let last = frame.len() - 1;
+        frame[last] ^= 0xFF;
+        let mut decoder = EventStreamDecoder::new();
+        assert!(matches!(
+            decoder.push(&frame),
+            Err(AppError::InvalidEventStreamFrame)
+        ));
+    }
+
+    #[test]
+    fn decode_payload_roundtrips_base64_bytes_field() {
+        let envelope = serde_json::json!({ "bytes": base64::engine::general_purpose::STANDARD.encode(b"chunk") });
+        let decoded = decode_payload(envelope.to_string().as_bytes()).unwrap();
+        assert_eq!(decoded, b"chunk");
+    }
+
+    #[test]
+    fn decode_payload_rejects_a_missing_bytes_field() {
+        let envelope = serde_json::json!({ "not_bytes": "x" });
+        assert!(matches!(
+            decode_payload(envelope.to_string().as_bytes()),
+            Err(AppError::InvalidEventStreamFrame)
+        ));
+    }
+}
@@ -0,0 +1,97 @@
+use super::Provider;
+use crate::config::{AuthScheme, ProviderConfig};
+use crate::error::AppError;
+use async_trait::async_trait;
+use axum::{body::Bytes, http::HeaderMap};
+use http::{header, HeaderName, HeaderValue};
+use tracing::{debug, error};
+
+/// A provider whose request/response shapes are already OpenAI-compatible, so
+/// routing it only requires a base URL, an auth scheme and optional header
+/// passthroughs — no custom body or path transformation. Covers `openai`,
+/// `anthropic`, `groq` and any self-hosted OpenAI-compatible endpoint added
+/// purely through `AppConfig`.
+pub struct GenericProvider {
+    name: String,
+    base_url: String,
+    auth_scheme: AuthScheme,
+    header_mappings: Vec<(String, String)>,
+}
+
+impl GenericProvider {
+    pub fn new(name: &str, config: &ProviderConfig) -> Self {
+        Self {
+            name: name.to_string(),
+            base_url: config.base_url.clone(),
+            auth_scheme: config.auth_scheme.clone(),
+            header_mappings: config.header_mappings.clone().into_iter().collect(),
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for GenericProvider {
+    fn base_url(&self, _headers: &HeaderMap) -> String {
+        self.base_url.clone()
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn prepare_request_body(&self, body: Bytes) -> Result<Bytes, AppError> {
+        Ok(body)
+    }
+
+    fn process_headers(&self, headers: &HeaderMap) -> Result<HeaderMap, AppError> {
+        let mut final_headers = HeaderMap::new();
+        final_headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+
+        match self.auth_scheme {
+            AuthScheme::BearerOrPassthrough => {
+                if let Some(api_key) = headers.get("x-magicapi-api-key").and_then(|h| h.to_str().ok()) {
+                    debug!(provider = %self.name, "Using x-magicapi-api-key for authentication");
+                    final_headers.insert(
+                        header::AUTHORIZATION,
+                        HeaderValue::from_str(&format!("Bearer {}", api_key))
+                            .map_err(|_| AppError::InvalidHeader)?,
+                    );
+                } else if let Some(auth) = headers.get(header::AUTHORIZATION) {
+                    debug!(provider = %self.name, "Using provided authorization header");
+                    final_headers.insert(header::AUTHORIZATION, auth.clone());
+                } else {
+                    error!(provider = %self.name, "No authorization header found");
+                    return Err(AppError::MissingApiKey);
+                }
+            }
+            AuthScheme::PassthroughAuthorization => {
+                let auth = headers
+                    .get(header::AUTHORIZATION)
+                    .ok_or_else(|| {
+                        error!(provider = %self.name, "No authorization header found");
+                        AppError::MissingApiKey
+                    })?;
+                final_headers.insert(header::AUTHORIZATION, auth.clone());
+            }
+            AuthScheme::Signed => {
+                // No auth header is added here, and this provider does not sign:
+                // its `requires_signing()` is `false`, so the proxy sends the
+                // request unsigned. Configs with `requires_signing` set are
+                // built as a `BedrockProvider` by the registry instead.
+            }
+        }
+
+        for (from, to) in &self.header_mappings {
+            if let Some(value) = headers.get(from.as_str()) {
+                let header_name = HeaderName::from_bytes(to.as_bytes()).map_err(|_| AppError::InvalidHeader)?;
+                final_headers.insert(header_name, value.clone());
+            }
+        }
+
+        Ok(final_headers)
+    }
+
+    fn transform_path(&self, path: &str, _stream: bool) -> String {
+        path.to_string()
+    }
+}
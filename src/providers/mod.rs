@@ -0,0 +1,89 @@
+pub mod bedrock;
+pub mod eventstream;
+pub mod generic;
+pub mod registry;
+
+use crate::error::AppError;
+use crate::signing::SigningCredentials;
+use async_trait::async_trait;
+use axum::{
+    body::Bytes,
+    http::HeaderMap,
+};
+use serde_json::Value;
+
+/// Common behavior every upstream model provider implements so the proxy layer
+/// can stay agnostic to each provider's request/response quirks.
+#[async_trait]
+pub trait Provider: Send + Sync {
+    /// The base URL to proxy to. Takes the client's request headers so
+    /// providers whose endpoint depends on a per-request value (e.g.
+    /// Bedrock's region) stay consistent with `get_signing_host` and
+    /// `get_signing_credentials`, which see the same headers.
+    fn base_url(&self, headers: &HeaderMap) -> String;
+
+    fn name(&self) -> &str;
+
+    /// Rewrites an incoming OpenAI-style request body into the shape this
+    /// provider expects on the wire.
+    async fn prepare_request_body(&self, body: Bytes) -> Result<Bytes, AppError>;
+
+    /// Builds the headers to send upstream from the headers the client sent us.
+    fn process_headers(&self, headers: &HeaderMap) -> Result<HeaderMap, AppError>;
+
+    /// Rewrites the request path, e.g. to inline a model id. `stream` reflects
+    /// the client's `"stream": true` request body field, for providers whose
+    /// streaming and non-streaming responses live at different endpoints (e.g.
+    /// Bedrock's `/invoke` vs `/invoke-with-response-stream`).
+    fn transform_path(&self, path: &str, stream: bool) -> String;
+
+    /// Resolves the model id to feed to `transform_response_body` and
+    /// `transform_streaming_chunk` - the model this request was actually
+    /// routed to, which isn't always the client's literal `model` field.
+    /// Defaults to that field (or `"unknown"` if absent), which is correct
+    /// for providers that forward `model` straight through. Providers that
+    /// resolve a different model server-side (e.g. Bedrock, whose
+    /// `transform_path` routes every `chat/completions` request to Titan
+    /// whatever the body's `model` says) must override this to match, or a successful
+    /// call looks like an `UnsupportedModel` error once the response comes
+    /// back.
+    fn resolve_model(&self, _path: &str, body: &Bytes) -> String {
+        serde_json::from_slice::<Value>(body)
+            .ok()
+            .and_then(|v| v["model"].as_str().map(str::to_string))
+            .unwrap_or_else(|| "unknown".to_string())
+    }
+
+    /// Whether outbound requests to this provider must be signed (e.g. AWS SigV4).
+    fn requires_signing(&self) -> bool {
+        false
+    }
+
+    /// Resolves the credentials used to sign the request. Only called when
+    /// `requires_signing()` returns `true`.
+    async fn get_signing_credentials(&self, _headers: &HeaderMap) -> Result<SigningCredentials, AppError> {
+        Err(AppError::UnsupportedProvider)
+    }
+
+    /// The `Host` header value to sign against, including a non-default port.
+    /// Must agree with `base_url` on whatever per-request value (e.g. region)
+    /// the endpoint depends on.
+    fn get_signing_host(&self, _headers: &HeaderMap) -> String {
+        String::new()
+    }
+
+    /// Converts this provider's non-streaming response body into an OpenAI
+    /// `chat.completion`-shaped body. Defaults to a no-op passthrough for
+    /// providers that are already OpenAI-compatible on the wire.
+    async fn transform_response_body(&self, _model: &str, body: Bytes) -> Result<Bytes, AppError> {
+        Ok(body)
+    }
+
+    /// Converts one already-framed chunk of this provider's native streaming
+    /// format into an OpenAI-compatible `data: {chat.completion.chunk}\n\n` SSE
+    /// event, or `None` if the chunk carries no visible delta. Defaults to a
+    /// no-op passthrough for providers that already stream `text/event-stream`.
+    fn transform_streaming_chunk(&self, _model: &str, chunk: Bytes) -> Result<Option<Bytes>, AppError> {
+        Ok(Some(chunk))
+    }
+}
@@ -0,0 +1,98 @@
+use super::bedrock::BedrockProvider;
+use super::generic::GenericProvider;
+use super::Provider;
+use crate::config::AppConfig;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Resolves a provider name from the proxy path to its `Provider` implementation.
+/// Built once from `AppConfig` at startup and shared (wrapped in an `Arc`) by
+/// every call to [`crate::proxy::proxy_request_to_provider`] - `AppConfig`
+/// doesn't change between requests, so there's no reason to reconstruct the
+/// `HashMap` and re-box every `Provider` on each one.
+pub struct ProviderRegistry {
+    providers: HashMap<String, Arc<dyn Provider>>,
+}
+
+impl ProviderRegistry {
+    pub fn from_config(config: &AppConfig) -> Self {
+        let mut providers: HashMap<String, Arc<dyn Provider>> = HashMap::new();
+        for (name, provider_config) in &config.providers {
+            // Dispatch on what the config says the provider needs, not on its
+            // registry key - a second signed entry named anything other than
+            // "bedrock" must still get signing, not silently fall through to
+            // GenericProvider's unsigned default.
+            let provider: Arc<dyn Provider> = if provider_config.requires_signing {
+                Arc::new(BedrockProvider::new(provider_config))
+            } else {
+                Arc::new(GenericProvider::new(name, provider_config))
+            };
+            providers.insert(name.clone(), provider);
+        }
+        Self { providers }
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn Provider>> {
+        self.providers.get(name).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AuthScheme, ProviderConfig};
+    use axum::http::HeaderMap;
+
+    fn signed_config() -> ProviderConfig {
+        ProviderConfig {
+            // A GovCloud partition domain - deliberately *not* what
+            // `BedrockProvider` would compute on its own from `default_region`
+            // (`bedrock-runtime.us-gov-west-1.amazonaws.com`), so the test only
+            // passes if this config's `base_url` is actually honored.
+            base_url: "https://bedrock-runtime.us-gov-west-1.amazonaws-us-gov.com".to_string(),
+            auth_scheme: AuthScheme::Signed,
+            requires_signing: true,
+            header_mappings: HashMap::new(),
+            default_region: Some("us-gov-west-1".to_string()),
+        }
+    }
+
+    #[test]
+    fn a_signed_config_is_routed_to_a_signing_provider_regardless_of_its_name() {
+        let mut config = AppConfig::new();
+        config.providers.clear();
+        config
+            .providers
+            .insert("bedrock-govcloud".to_string(), signed_config());
+
+        let registry = ProviderRegistry::from_config(&config);
+        let provider = registry.get("bedrock-govcloud").expect("provider registered");
+
+        assert!(provider.requires_signing());
+        assert_eq!(
+            provider.base_url(&HeaderMap::new()),
+            "https://bedrock-runtime.us-gov-west-1.amazonaws-us-gov.com"
+        );
+    }
+
+    #[test]
+    fn an_unsigned_config_is_routed_to_a_generic_provider() {
+        let mut config = AppConfig::new();
+        config.providers.clear();
+        config.providers.insert(
+            "self-hosted".to_string(),
+            ProviderConfig {
+                base_url: "https://models.internal".to_string(),
+                auth_scheme: AuthScheme::BearerOrPassthrough,
+                requires_signing: false,
+                header_mappings: HashMap::new(),
+                default_region: None,
+            },
+        );
+
+        let registry = ProviderRegistry::from_config(&config);
+        let provider = registry.get("self-hosted").expect("provider registered");
+
+        assert!(!provider.requires_signing());
+    }
+}
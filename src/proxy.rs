@@ -7,214 +7,250 @@ use http::HeaderValue;
 use std::str::FromStr;
 use crate::config::AppConfig;
 use crate::error::AppError;
+use crate::metrics;
+use crate::providers::eventstream::EventStreamDecoder;
+use crate::providers::registry::ProviderRegistry;
+use crate::providers::{eventstream, Provider};
+use crate::proxy::client::CLIENT;
+use crate::retry;
+use crate::signing;
+use serde_json::Value;
 use tracing::{info, error};
-use std::time::Duration;
-use once_cell::sync::Lazy;
+use std::time::Instant;
 use futures_util::StreamExt;
 
-/// Static HTTP client with optimized connection pooling and timeout settings
-static CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
-    reqwest::Client::builder()
-        .pool_idle_timeout(Duration::from_secs(30))
-        .pool_max_idle_per_host(32)
-        .tcp_keepalive(Duration::from_secs(60))
-        .timeout(Duration::from_secs(30))
-        .build()
-        .expect("Failed to create HTTP client")
-});
-
-/// Proxies incoming requests to the specified provider while maintaining optimal performance
-/// through connection pooling and efficient streaming.
+pub mod client;
+
+/// Decrements the in-flight gauge for `provider` when dropped, so the gauge
+/// stays accurate even if a request returns early via `?`. For streamed
+/// responses the guard must be moved into the stream itself (see its capture
+/// in the `bytes_stream().then(...)` closure below) rather than left in the
+/// function's local scope, or the gauge decrements as soon as the upstream
+/// headers arrive instead of when the client finishes reading the body.
+struct InFlightGuard(String);
+
+impl InFlightGuard {
+    fn new(provider: &str) -> Self {
+        metrics::IN_FLIGHT.with_label_values(&[provider]).inc();
+        Self(provider.to_string())
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        metrics::IN_FLIGHT.with_label_values(&[&self.0]).dec();
+    }
+}
+
+fn copy_response_headers(response: &reqwest::Response) -> HeaderMap {
+    let mut response_headers = HeaderMap::new();
+    for (name, value) in response.headers() {
+        if let Ok(v) = HeaderValue::from_bytes(value.as_bytes()) {
+            if let Ok(header_name) = http::HeaderName::from_bytes(name.as_ref()) {
+                response_headers.insert(header_name, v);
+            } else {
+                tracing::warn!("Failed to convert header name: {:?}", name);
+            }
+        } else {
+            tracing::warn!("Failed to convert header value for: {:?}", name);
+        }
+    }
+    response_headers
+}
+
+/// Proxies an incoming request to the named provider, resolved from a
+/// `ProviderRegistry` built once off `AppConfig` and shared across requests
+/// by the caller - see [`ProviderRegistry::from_config`]. All providers -
+/// whether an OpenAI-compatible `GenericProvider` or a trait-object provider
+/// like `BedrockProvider` with its own signing and body transforms - flow
+/// through the same path/header/body/signing pipeline, so adding a new
+/// provider is a config change rather than a new match arm here.
 pub async fn proxy_request_to_provider(
-    _config: Arc<AppConfig>,
-    provider: &str,
+    config: Arc<AppConfig>,
+    registry: Arc<ProviderRegistry>,
+    provider_name: &str,
     original_request: Request<Body>,
 ) -> Result<Response<Body>, AppError> {
     info!(
-        provider = provider,
+        provider = provider_name,
         method = %original_request.method(),
         path = %original_request.uri().path(),
         "Incoming request"
     );
 
-    let base_url = match provider {
-        "openai" => "https://api.openai.com",
-        "anthropic" => "https://api.anthropic.com",
-        "groq" => "https://api.groq.com/openai",
-        _ => {
-            error!(provider = provider, "Unsupported provider");
-            return Err(AppError::UnsupportedProvider);
-        }
-    };
+    let provider = registry.get(provider_name).ok_or_else(|| {
+        error!(provider = provider_name, "Unsupported provider");
+        AppError::UnsupportedProvider
+    })?;
 
-    let path = original_request.uri().path();
+    let original_headers = original_request.headers().clone();
+    let original_path = original_request.uri().path().to_string();
     let query = original_request
         .uri()
         .query()
         .map(|q| format!("?{}", q))
         .unwrap_or_default();
 
-    let url = format!("{}{}{}", base_url, path, query);
-    info!(
-        provider = provider,
-        url = %url,
-        method = %original_request.method(),
-        "Preparing proxy request"
-    );
-
     let method = reqwest::Method::from_str(original_request.method().as_str())
         .map_err(|_| AppError::InvalidMethod)?;
-    
-    // Optimize headers handling with pre-allocated capacity
-    let mut reqwest_headers = reqwest::header::HeaderMap::with_capacity(8);
-    reqwest_headers.insert(
-        reqwest::header::CONTENT_TYPE,
-        reqwest::header::HeaderValue::from_static("application/json"),
-    );
 
-    // Header handling for different providers
-    match provider {
-        "openai" => {
-            tracing::debug!("Processing OpenAI request headers");
-            if let Some(api_key) = original_request.headers().get("x-magicapi-api-key")
-                .and_then(|h| h.to_str().ok()) {
-                tracing::debug!("Using x-magicapi-api-key for authentication");
-                reqwest_headers.insert(
-                    reqwest::header::AUTHORIZATION,
-                    reqwest::header::HeaderValue::from_str(&format!("Bearer {}", api_key))
-                        .map_err(|_| {
-                            tracing::error!("Failed to create authorization header from x-magicapi-api-key");
-                            AppError::InvalidHeader
-                        })?
-                );
-            } else if let Some(auth) = original_request.headers().get("authorization")
-                .and_then(|h| h.to_str().ok()) {
-                tracing::debug!("Using provided authorization header");
-                reqwest_headers.insert(
-                    reqwest::header::AUTHORIZATION,
-                    reqwest::header::HeaderValue::from_str(auth)
-                        .map_err(|_| {
-                            tracing::error!("Failed to process authorization header");
-                            AppError::InvalidHeader
-                        })?
-                );
-            } else {
-                tracing::error!("No authorization header found for OpenAI request");
-                return Err(AppError::MissingApiKey);
-            }
-        },
-        "groq" => {
-            tracing::debug!("Processing GROQ request headers");
-            if let Some(auth) = original_request.headers().get("authorization")
-                .and_then(|h| h.to_str().ok()) {
-                tracing::debug!("Using provided authorization header for GROQ");
-                reqwest_headers.insert(
-                    reqwest::header::AUTHORIZATION,
-                    reqwest::header::HeaderValue::from_str(auth)
-                        .map_err(|_| {
-                            tracing::error!("Failed to process GROQ authorization header");
-                            AppError::InvalidHeader
-                        })?
-                );
-            } else {
-                tracing::error!("No authorization header found for GROQ request");
-                return Err(AppError::MissingApiKey);
-            }
-        },
-        _ => return Err(AppError::UnsupportedProvider),
-    }
-
-    tracing::info!("Proxying request to {}", url);
-    // Efficiently handle request body
     let body_bytes = body::to_bytes(original_request.into_body(), usize::MAX).await?;
     tracing::debug!("Request body size: {} bytes", body_bytes.len());
-    
-    let proxy_request = CLIENT
-        .request(method, url)
-        .headers(reqwest_headers)
-        .body(body_bytes.to_vec());
+    let stream = serde_json::from_slice::<Value>(&body_bytes)
+        .ok()
+        .and_then(|v| v["stream"].as_bool())
+        .unwrap_or(false);
+
+    let path = provider.transform_path(&original_path, stream);
+    // The model actually routed to, not just the client's raw `model` field -
+    // see `Provider::resolve_model`. Used for metrics as well as the response
+    // transforms below, so both agree with what was really sent upstream.
+    let model = provider.resolve_model(&original_path, &body_bytes);
+    let url = format!("{}{}{}", provider.base_url(&original_headers), path, query);
+    info!(provider = provider_name, url = %url, stream, "Preparing proxy request");
+
+    let request_bytes = body_bytes.len();
+    let prepared_body = provider.prepare_request_body(body_bytes).await?;
+    let mut headers = provider.process_headers(&original_headers)?;
+
+    if provider.requires_signing() {
+        let host = provider.get_signing_host(&original_headers);
+        let credentials = provider.get_signing_credentials(&original_headers).await?;
+        signing::sign_request(
+            method.as_str(),
+            &path,
+            query.trim_start_matches('?'),
+            &host,
+            &mut headers,
+            &prepared_body,
+            &credentials,
+        )?;
+    }
+
+    metrics::REQUESTS_TOTAL.with_label_values(&[provider_name, &model]).inc();
+    metrics::BYTES_IN_TOTAL.with_label_values(&[provider_name]).inc_by(request_bytes as f64);
+    let in_flight = InFlightGuard::new(provider_name);
 
     tracing::debug!("Sending request to provider");
-    let response = proxy_request.send().await.map_err(|e| {
-        tracing::error!("Provider request failed: {}", e);
-        e
-    })?;
+    let request_started_at = Instant::now();
+    let response = retry::send_with_retry(&CLIENT, &method, &url, &headers, &prepared_body, &config)
+        .await
+        .map_err(|e| {
+            tracing::error!("Provider request failed: {}", e);
+            e
+        })?;
+    metrics::UPSTREAM_LATENCY_SECONDS
+        .with_label_values(&[provider_name, &model])
+        .observe(request_started_at.elapsed().as_secs_f64());
+
     let status = StatusCode::from_u16(response.status().as_u16())?;
-    tracing::info!("Provider response status: {}", status);
+    info!(provider = provider_name, status = %status, "Provider response status");
+    if !status.is_success() {
+        metrics::ERRORS_TOTAL
+            .with_label_values(&[provider_name, metrics::status_class(status.as_u16())])
+            .inc();
+    }
 
-    // Optimize streaming response handling
-    if response.headers()
+    let content_type = response
+        .headers()
         .get(reqwest::header::CONTENT_TYPE)
         .and_then(|v| v.to_str().ok())
-        .map_or(false, |ct| ct.contains("text/event-stream")) 
-    {
+        .unwrap_or("")
+        .to_string();
+    let is_eventstream_framed = content_type.contains("vnd.amazon.eventstream");
+    let is_streaming = is_eventstream_framed || content_type.contains("text/event-stream");
+
+    if is_streaming {
         tracing::info!("Processing streaming response");
-        // Efficient headers copying with proper type conversion
-        let mut response_headers = HeaderMap::new();
-        for (name, value) in response.headers() {
-            if let Ok(v) = HeaderValue::from_bytes(value.as_bytes()) {
-                if let Ok(header_name) = http::HeaderName::from_bytes(name.as_ref()) {
-                    response_headers.insert(header_name, v);
-                } else {
-                    tracing::warn!("Failed to convert header name: {:?}", name);
-                }
-            } else {
-                tracing::warn!("Failed to convert header value for: {:?}", name);
-            }
-        }
+        metrics::STREAMING_SPLIT_TOTAL.with_label_values(&[provider_name, "streaming"]).inc();
+        let response_headers = copy_response_headers(&response);
 
-        tracing::debug!("Setting up streaming response");
-        // Efficient stream handling with proper error mapping
-        let stream = response.bytes_stream()
-            .map(|result| {
-                match result {
-                    Ok(bytes) => {
-                        tracing::trace!("Streaming chunk: {} bytes", bytes.len());
-                        Ok(Bytes::from(bytes))
-                    },
-                    Err(e) => {
-                        tracing::error!("Stream error: {}", e);
-                        Err(std::io::Error::new(std::io::ErrorKind::Other, e))
+        let provider = provider.clone();
+        let stream_provider_name = provider_name.to_string();
+        let decoder = is_eventstream_framed
+            .then(|| Arc::new(tokio::sync::Mutex::new(EventStreamDecoder::new())));
+
+        // Captured into this closure rather than left in the outer function
+        // scope, so the gauge stays incremented for as long as the client is
+        // still reading the streamed body, not just until the stream is set up.
+        let chunks = response.bytes_stream().then(move |result| {
+            let provider = provider.clone();
+            let decoder = decoder.clone();
+            let model = model.clone();
+            let provider_name = stream_provider_name.clone();
+            let _in_flight = &in_flight;
+            async move {
+                let bytes = result.map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+                let mut out = Vec::new();
+
+                if let Some(decoder) = decoder {
+                    let mut decoder = decoder.lock().await;
+                    let frames = decoder
+                        .push(&bytes)
+                        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+                    for frame in frames {
+                        let payload = eventstream::decode_payload(&frame)
+                            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+                        if let Some(sse) = provider
+                            .transform_streaming_chunk(&model, Bytes::from(payload))
+                            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?
+                        {
+                            out.extend_from_slice(&sse);
+                        }
                     }
+                } else if let Some(sse) = provider
+                    .transform_streaming_chunk(&model, Bytes::from(bytes))
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?
+                {
+                    out.extend_from_slice(&sse);
                 }
-            });
 
-        tracing::debug!("Returning streaming response");
-        Ok(Response::builder()
+                metrics::BYTES_OUT_TOTAL.with_label_values(&[&provider_name]).inc_by(out.len() as f64);
+                Ok::<Bytes, std::io::Error>(Bytes::from(out))
+            }
+        });
+
+        let stream: std::pin::Pin<Box<dyn futures_util::Stream<Item = Result<Bytes, std::io::Error>> + Send>> =
+            if is_eventstream_framed {
+                let done = futures_util::stream::once(async {
+                    Ok::<Bytes, std::io::Error>(Bytes::from_static(b"data: [DONE]\n\n"))
+                });
+                Box::pin(chunks.chain(done))
+            } else {
+                Box::pin(chunks)
+            };
+
+        return Ok(Response::builder()
             .status(status)
             .header("content-type", "text/event-stream")
             .header("cache-control", "no-cache")
             .header("connection", "keep-alive")
             .extension(response_headers)
             .body(Body::from_stream(stream))
-            .unwrap())
-    } else {
-        // Extract headers before consuming the response body
-        let mut response_headers = HeaderMap::new();
-        for (name, value) in response.headers() {
-            if let Ok(v) = HeaderValue::from_bytes(value.as_bytes()) {
-                if let Ok(header_name) = http::HeaderName::from_bytes(name.as_ref()) {
-                    response_headers.insert(header_name, v);
-                } else {
-                    tracing::warn!("Failed to convert header name: {:?}", name);
-                }
-            } else {
-                tracing::warn!("Failed to convert header value for: {:?}", name);
-            }
-        }
+            .unwrap());
+    }
 
-        // Now consume the response body
-        let body = response.bytes().await?;
+    let response_headers = copy_response_headers(&response);
+    let body = response.bytes().await?;
+    // Only the provider's own success shape is safe to reinterpret as a model
+    // result - e.g. the titan branch reaches into `value["results"][0]` and a
+    // Bedrock error body has no such field, and the anthropic branch would
+    // otherwise turn an upstream error into an empty `assistant` message.
+    // Pass error bodies through untouched so the client sees the real error.
+    let body = if status.is_success() {
+        provider.transform_response_body(&model, body).await?
+    } else {
+        body
+    };
 
-        let mut builder = Response::builder().status(status);
-        
-        // Add headers individually to the builder
-        for (name, value) in response_headers.iter() {
-            builder = builder.header(name, value);
-        }
+    metrics::STREAMING_SPLIT_TOTAL.with_label_values(&[provider_name, "buffered"]).inc();
+    metrics::BYTES_OUT_TOTAL.with_label_values(&[provider_name]).inc_by(body.len() as f64);
+    metrics::record_token_usage(provider_name, &model, &body);
 
-        Ok(builder
-            .body(Body::from(body))
-            .unwrap())
+    let mut builder = Response::builder().status(status);
+    for (name, value) in response_headers.iter() {
+        builder = builder.header(name, value);
     }
-} 
\ No newline at end of file
+
+    Ok(builder.body(Body::from(body)).unwrap())
+}
@@ -25,6 +25,9 @@ pub fn create_client(config: &AppConfig) -> reqwest::Client {
         .expect("Failed to create HTTP client")
 }
 
+/// The single pooled HTTP client shared by every provider `proxy_request_to_provider`
+/// dispatches to, whether signed (Bedrock) or not - there is no longer a
+/// provider-specific client to keep in sync with this one.
 pub static CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
     let config = AppConfig::new();
     create_client(&config)
@@ -0,0 +1,175 @@
+use crate::config::AppConfig;
+use rand::Rng;
+use reqwest::{header::HeaderMap, Client, Method};
+use std::time::Duration;
+use tracing::warn;
+
+/// Whether `method` is safe to blindly replay against an upstream that may
+/// already have received and acted on the first attempt. POST is excluded: a
+/// provider that timed out *after* generating a completion would otherwise be
+/// billed and replayed twice.
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS
+    )
+}
+
+/// Status codes that reliably mean the upstream rejected the request before
+/// doing any (side-effecting) work, rather than failing partway through
+/// generating a completion, so they're safe to replay even for a
+/// non-idempotent POST. 429 (rate limited) qualifies unconditionally; 503
+/// does not - unlike a provider's own "overloaded" 503, one raised by an
+/// intermediary/load balancer can follow a completion that was already
+/// generated, and replaying then would double-bill or duplicate it. A
+/// timeout or any other retryable status (including 503) is only replayed
+/// for idempotent methods.
+const SAFE_TO_REPLAY_FOR_ANY_METHOD: &[u16] = &[429];
+
+/// Whether a response with `status` is safe to replay for `method`: either the
+/// method is idempotent, or the status itself guarantees the upstream did no
+/// work on the first attempt.
+fn is_replayable_status(method: &Method, status: u16) -> bool {
+    is_idempotent(method) || SAFE_TO_REPLAY_FOR_ANY_METHOD.contains(&status)
+}
+
+/// Sends a fully-buffered request, replaying it up to `config.max_retries` times
+/// on connection errors or a retryable status code. Honors `Retry-After` on
+/// 429/503 responses and otherwise backs off exponentially with jitter.
+///
+/// Only safe to call before any response bytes have been streamed back to the
+/// client, since a replay here re-sends the whole buffered request body. A
+/// connection error (the request never reached the upstream) is always safe
+/// to retry, as is a 429 (see `is_replayable_status`) regardless of method; a
+/// timeout or any other retryable status, including 503, is only replayed for
+/// idempotent methods, since for a non-idempotent method like POST the
+/// upstream may have already processed the request before failing or
+/// timing out.
+pub async fn send_with_retry(
+    client: &Client,
+    method: &Method,
+    url: &str,
+    headers: &HeaderMap,
+    body: &[u8],
+    config: &AppConfig,
+) -> Result<reqwest::Response, reqwest::Error> {
+    let idempotent = is_idempotent(method);
+    let mut attempt = 0u32;
+    loop {
+        let result = client
+            .request(method.clone(), url)
+            .headers(headers.clone())
+            .body(body.to_vec())
+            .send()
+            .await;
+
+        match result {
+            Ok(response)
+                if attempt < config.max_retries
+                    && config
+                        .retryable_status_codes
+                        .contains(&response.status().as_u16())
+                    && is_replayable_status(method, response.status().as_u16()) =>
+            {
+                let delay = retry_after_delay(&response)
+                    .unwrap_or_else(|| backoff_delay(attempt, config.retry_base_delay_ms));
+                warn!(
+                    attempt = attempt + 1,
+                    max_retries = config.max_retries,
+                    status = %response.status(),
+                    delay_ms = delay.as_millis() as u64,
+                    "Retrying upstream request after a retryable status"
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Ok(response) => return Ok(response),
+            Err(e) if attempt < config.max_retries && e.is_connect() => {
+                let delay = backoff_delay(attempt, config.retry_base_delay_ms);
+                warn!(
+                    attempt = attempt + 1,
+                    max_retries = config.max_retries,
+                    error = %e,
+                    delay_ms = delay.as_millis() as u64,
+                    "Retrying upstream request after a connection error"
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) if idempotent && attempt < config.max_retries && e.is_timeout() => {
+                let delay = backoff_delay(attempt, config.retry_base_delay_ms);
+                warn!(
+                    attempt = attempt + 1,
+                    max_retries = config.max_retries,
+                    error = %e,
+                    delay_ms = delay.as_millis() as u64,
+                    "Retrying upstream request after a timeout"
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn backoff_delay(attempt: u32, base_delay_ms: u64) -> Duration {
+    let exponential = base_delay_ms.saturating_mul(1u64 << attempt.min(16));
+    let jitter = rand::thread_rng().gen_range(0..=(exponential / 2).max(1));
+    Duration::from_millis(exponential + jitter)
+}
+
+fn retry_after_delay(response: &reqwest::Response) -> Option<Duration> {
+    let raw = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?;
+    raw.parse::<u64>().ok().map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_head_put_delete_options_are_idempotent() {
+        assert!(is_idempotent(&Method::GET));
+        assert!(is_idempotent(&Method::HEAD));
+        assert!(is_idempotent(&Method::PUT));
+        assert!(is_idempotent(&Method::DELETE));
+        assert!(is_idempotent(&Method::OPTIONS));
+    }
+
+    #[test]
+    fn post_and_patch_are_not_idempotent() {
+        assert!(!is_idempotent(&Method::POST));
+        assert!(!is_idempotent(&Method::PATCH));
+    }
+
+    #[test]
+    fn any_method_replays_a_429() {
+        assert!(is_replayable_status(&Method::POST, 429));
+        assert!(is_replayable_status(&Method::GET, 429));
+    }
+
+    #[test]
+    fn post_does_not_replay_a_503() {
+        // A 503 can occur after an upstream already generated a completion,
+        // so a non-idempotent POST must not be blindly replayed on one -
+        // replaying would risk double-billing or duplicating the response.
+        assert!(!is_replayable_status(&Method::POST, 503));
+    }
+
+    #[test]
+    fn idempotent_method_replays_a_503() {
+        assert!(is_replayable_status(&Method::GET, 503));
+    }
+
+    #[test]
+    fn post_does_not_replay_other_5xx_statuses() {
+        assert!(!is_replayable_status(&Method::POST, 500));
+        assert!(!is_replayable_status(&Method::POST, 502));
+        assert!(!is_replayable_status(&Method::POST, 504));
+    }
+}
@@ -0,0 +1,268 @@
+use crate::error::AppError;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use http::{HeaderMap, HeaderValue};
+use sha2::{Digest, Sha256};
+use tracing::debug;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// URI-encodes raw bytes per the SigV4 `UriEncode` rules (RFC 3986 unreserved
+/// characters pass through unescaped; everything else, including `:`, is
+/// percent-encoded). Model ids like `anthropic.claude-3-sonnet-20240229-v1:0`
+/// contain `:`, which AWS signs as `%3A` - signing the raw byte instead
+/// yields `SignatureDoesNotMatch`.
+fn uri_encode_bytes(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// URI-encodes a single already-unescaped path segment. See [`uri_encode_bytes`].
+fn uri_encode_segment(segment: &str) -> String {
+    uri_encode_bytes(segment.as_bytes())
+}
+
+/// Percent-encodes a canonical URI path segment-by-segment, preserving `/`
+/// separators, per the SigV4 canonical request rules.
+fn canonical_uri_path(path: &str) -> String {
+    path.split('/')
+        .map(uri_encode_segment)
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Percent-decodes a raw (possibly already percent-encoded) query-string
+/// key or value into the bytes it represents, so it can be re-encoded per
+/// the `UriEncode` rules below. `+` is left as a literal `+` - SigV4 query
+/// canonicalization is not `application/x-www-form-urlencoded`.
+fn percent_decode(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let Some(decoded) = std::str::from_utf8(&bytes[i + 1..i + 3])
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                out.push(decoded);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    out
+}
+
+/// Canonicalizes a request's raw query string per the SigV4 canonical
+/// request rules: each `key=value` pair is percent-decoded then re-encoded
+/// with [`uri_encode_bytes`], and the pairs are sorted by the encoded key
+/// (then value). Using the raw query string verbatim only happens to work
+/// when it's empty - Bedrock's `invoke`/`invoke-with-response-stream` never
+/// carry one - but any other query param would sign a canonical request that
+/// disagrees with what AWS computes.
+fn canonical_query_string(query: &str) -> String {
+    if query.is_empty() {
+        return String::new();
+    }
+    let mut pairs: Vec<(String, String)> = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (
+                uri_encode_bytes(&percent_decode(key.as_bytes())),
+                uri_encode_bytes(&percent_decode(value.as_bytes())),
+            )
+        })
+        .collect();
+    pairs.sort();
+    pairs
+        .into_iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Credentials used to derive an AWS SigV4 signature for a single request.
+pub struct SigningCredentials {
+    pub access_key: String,
+    pub secret_key: String,
+    pub session_token: Option<String>,
+    pub region: String,
+}
+
+fn hex_sha256(bytes: &[u8]) -> String {
+    let digest = Sha256::digest(bytes);
+    hex::encode(digest)
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Signs a request for AWS Bedrock using SigV4 and inserts the `Authorization`,
+/// `x-amz-date` and `x-amz-content-sha256` headers (plus `x-amz-security-token`
+/// when a session token is present) directly into `headers`.
+///
+/// `host` must match [`crate::providers::Provider::get_signing_host`] exactly,
+/// including any non-default port, and `body` must be the exact bytes that will
+/// be sent on the wire (i.e. after `prepare_request_body`).
+pub fn sign_request(
+    method: &str,
+    uri_path: &str,
+    query: &str,
+    host: &str,
+    headers: &mut HeaderMap,
+    body: &[u8],
+    credentials: &SigningCredentials,
+) -> Result<(), AppError> {
+    let service = "bedrock";
+    let now = Utc::now();
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date_stamp = now.format("%Y%m%d").to_string();
+
+    let payload_hash = hex_sha256(body);
+
+    headers.insert(
+        "x-amz-date",
+        HeaderValue::from_str(&amz_date).map_err(|_| AppError::InvalidHeader)?,
+    );
+    headers.insert(
+        "x-amz-content-sha256",
+        HeaderValue::from_str(&payload_hash).map_err(|_| AppError::InvalidHeader)?,
+    );
+    headers.insert(
+        http::header::HOST,
+        HeaderValue::from_str(host).map_err(|_| AppError::InvalidHeader)?,
+    );
+    if let Some(token) = &credentials.session_token {
+        headers.insert(
+            "x-amz-security-token",
+            HeaderValue::from_str(token).map_err(|_| AppError::InvalidHeader)?,
+        );
+    }
+
+    // Canonical headers must be lowercased, trimmed and sorted by name.
+    let mut canonical_pairs: Vec<(String, String)> = headers
+        .iter()
+        .map(|(name, value)| {
+            let value = value.to_str().unwrap_or("").trim().to_string();
+            (name.as_str().to_lowercase(), value)
+        })
+        .collect();
+    canonical_pairs.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let canonical_headers: String = canonical_pairs
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value))
+        .collect();
+    let signed_headers = canonical_pairs
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>()
+        .join(";");
+
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method,
+        canonical_uri_path(uri_path),
+        canonical_query_string(query),
+        canonical_headers,
+        signed_headers,
+        payload_hash
+    );
+    debug!("Canonical request: {}", canonical_request);
+
+    let credential_scope = format!(
+        "{}/{}/{}/aws4_request",
+        date_stamp, credentials.region, service
+    );
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        credential_scope,
+        hex_sha256(canonical_request.as_bytes())
+    );
+
+    let k_date = hmac_sha256(
+        format!("AWS4{}", credentials.secret_key).as_bytes(),
+        date_stamp.as_bytes(),
+    );
+    let k_region = hmac_sha256(&k_date, credentials.region.as_bytes());
+    let k_service = hmac_sha256(&k_region, service.as_bytes());
+    let k_signing = hmac_sha256(&k_service, b"aws4_request");
+    let signature = hex::encode(hmac_sha256(&k_signing, string_to_sign.as_bytes()));
+
+    let authorization = format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        credentials.access_key, credential_scope, signed_headers, signature
+    );
+    headers.insert(
+        http::header::AUTHORIZATION,
+        HeaderValue::from_str(&authorization).map_err(|_| AppError::InvalidHeader)?,
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uri_encode_bytes_preserves_unreserved_and_encodes_colon() {
+        assert_eq!(
+            uri_encode_bytes(b"anthropic.claude-3-sonnet-v1:0"),
+            "anthropic.claude-3-sonnet-v1%3A0"
+        );
+        assert_eq!(uri_encode_bytes(b"a b"), "a%20b");
+    }
+
+    #[test]
+    fn canonical_uri_path_encodes_segments_not_slashes() {
+        assert_eq!(
+            canonical_uri_path("/model/anthropic.claude-v2:1/invoke"),
+            "/model/anthropic.claude-v2%3A1/invoke"
+        );
+    }
+
+    #[test]
+    fn canonical_query_string_sorts_pairs_and_reencodes_values() {
+        assert_eq!(canonical_query_string(""), "");
+        assert_eq!(canonical_query_string("b=2&a=1"), "a=1&b=2");
+        assert_eq!(canonical_query_string("key=a:b"), "key=a%3Ab");
+    }
+
+    #[test]
+    fn signing_key_matches_aws_published_test_vector() {
+        // https://docs.aws.amazon.com/general/latest/gr/sigv4-calculate-signature.html
+        let secret_key = "wJalrXUtnFEMI/K7MDENG/bPxRfiCYEXAMPLEKEY";
+        let date_stamp = "20120215";
+        let region = "us-east-1";
+        let service = "iam";
+
+        let k_date = hmac_sha256(
+            format!("AWS4{}", secret_key).as_bytes(),
+            date_stamp.as_bytes(),
+        );
+        let k_region = hmac_sha256(&k_date, region.as_bytes());
+        let k_service = hmac_sha256(&k_region, service.as_bytes());
+        let k_signing = hmac_sha256(&k_service, b"aws4_request");
+
+        assert_eq!(
+            hex::encode(k_signing),
+            "004aa806e13dae88b9032d9261bcb04c67d023afadd221e6b0d206e1760e0b5e"
+        );
+    }
+}